@@ -0,0 +1,111 @@
+use std::{
+    path::PathBuf,
+    sync::{atomic::AtomicUsize, Arc},
+};
+
+use structopt::StructOpt;
+
+use crate::path_filter::PathFilter;
+
+/// Command line arguments, as parsed by `structopt`.
+#[derive(StructOpt, Debug)]
+#[structopt(name = "miniserve", about = "A CLI tool to serve static files over HTTP")]
+pub struct Args {
+    /// Which path to serve
+    #[structopt(name = "PATH", parse(from_os_str))]
+    pub path: Option<PathBuf>,
+
+    /// Allow uploads to overwrite any existing file with the same name
+    #[structopt(long = "overwrite-files")]
+    pub overwrite_files: bool,
+
+    /// Restrict uploads to files whose content matches one of these signatures (comma-separated;
+    /// one or more of png, jpeg, gif, pdf, webp, mp4). Leave empty to accept any content.
+    #[structopt(long = "allowed-upload-types", use_delimiter = true)]
+    pub allowed_upload_types: Vec<String>,
+
+    /// Allow paths matching this glob (supports a single `*` wildcard); may be repeated. Applied
+    /// to both uploads and the directory listing. All `--path-filter-allow` rules are tried
+    /// before any `--path-filter-deny` rule.
+    #[structopt(long = "path-filter-allow")]
+    pub path_filter_allow: Vec<String>,
+
+    /// Deny paths matching this glob (supports a single `*` wildcard); may be repeated, see
+    /// `--path-filter-allow`.
+    #[structopt(long = "path-filter-deny")]
+    pub path_filter_deny: Vec<String>,
+
+    /// Hide and reject any path with a dotfile component (e.g. `.git`, `.env`), regardless of the
+    /// allow/deny rules above.
+    #[structopt(long = "deny-dotfiles")]
+    pub deny_dotfiles: bool,
+
+    /// Reject uploads larger than this many bytes.
+    #[structopt(long = "upload-size-limit")]
+    pub max_upload_size: Option<u64>,
+
+    /// Reject new uploads once this many are being streamed to disk at once.
+    #[structopt(long = "max-concurrent-uploads")]
+    pub max_concurrent_uploads: Option<usize>,
+}
+
+/// Runtime configuration for miniserve, built from parsed CLI arguments and shared as actix-web
+/// application state. Cheap to clone: every field is either `Copy`, owned data shared behind an
+/// `Arc`, or small enough that per-worker cloning is a non-issue.
+#[derive(Clone)]
+pub struct MiniserveConfig {
+    /// Directory served.
+    pub path: PathBuf,
+
+    /// Whether an uploaded file may overwrite an existing one.
+    pub overwrite_files: bool,
+
+    /// File signatures an uploaded file's content must match, if set.
+    pub allowed_upload_types: Option<Vec<String>>,
+
+    /// Allow/deny predicate consulted before a path is uploaded to or listed.
+    pub path_filter: Option<PathFilter>,
+
+    /// Maximum size, in bytes, of a single upload.
+    pub max_upload_size: Option<u64>,
+
+    /// Maximum number of uploads that may be streamed to disk at once.
+    pub max_concurrent_uploads: Option<usize>,
+
+    /// Number of uploads currently being streamed to disk, shared across all requests.
+    pub in_flight_uploads: Arc<AtomicUsize>,
+}
+
+impl From<Args> for MiniserveConfig {
+    fn from(args: Args) -> Self {
+        let path_filter = if args.path_filter_allow.is_empty()
+            && args.path_filter_deny.is_empty()
+            && !args.deny_dotfiles
+        {
+            None
+        } else {
+            let mut filter = PathFilter::new().deny_dotfiles(args.deny_dotfiles);
+            for pattern in args.path_filter_allow {
+                filter = filter.allow(pattern);
+            }
+            for pattern in args.path_filter_deny {
+                filter = filter.deny(pattern);
+            }
+            Some(filter)
+        };
+
+        MiniserveConfig {
+            path: args.path.unwrap_or_else(|| PathBuf::from(".")),
+            overwrite_files: args.overwrite_files,
+            allowed_upload_types: if args.allowed_upload_types.is_empty() {
+                None
+            } else {
+                Some(args.allowed_upload_types)
+            },
+            path_filter,
+            max_upload_size: args.max_upload_size,
+            max_concurrent_uploads: args.max_concurrent_uploads,
+            in_flight_uploads: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt;
+
+/// An error, together with enough context to turn it into an actionable message shown to the
+/// client and logged on the server.
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum ContextualError {
+    /// Any other error that doesn't warrant its own variant.
+    CustomError(String),
+
+    /// An I/O error, tagged with a description of what miniserve was doing when it occurred.
+    IOError(String, std::io::Error),
+
+    /// A malformed multipart request body.
+    MultipartError(actix_web::error::MultipartError),
+
+    /// The request was missing something miniserve needs to handle it (a query parameter, a
+    /// header, ...), or a header was present but invalid.
+    InvalidHTTPRequestError(String),
+
+    /// A path given to miniserve on the command line or by a request could not be used.
+    InvalidPathError(String),
+
+    /// The server process lacks the permissions it needs to read or write a path.
+    InsufficientPermissionsError(String),
+
+    /// A request header failed to parse.
+    ParseError,
+
+    /// A path was rejected by the configured allow/deny filter.
+    ForbiddenPathError(String),
+
+    /// The content of an uploaded file did not match any of the configured allowed file
+    /// signatures.
+    DisallowedUploadTypeError(String),
+
+    /// An upload exceeded the configured maximum size, in bytes.
+    UploadTooLargeError(String, u64),
+}
+
+impl fmt::Display for ContextualError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContextualError::CustomError(message) => write!(f, "{}", message),
+            ContextualError::IOError(context, e) => write!(f, "{}: {}", context, e),
+            ContextualError::MultipartError(e) => write!(f, "Invalid multipart request: {}", e),
+            ContextualError::InvalidHTTPRequestError(message) => write!(f, "{}", message),
+            ContextualError::InvalidPathError(path) => write!(f, "Invalid path: {}", path),
+            ContextualError::InsufficientPermissionsError(path) => {
+                write!(f, "Insufficient permissions to access {}", path)
+            }
+            ContextualError::ParseError => write!(f, "Failed to parse request"),
+            ContextualError::ForbiddenPathError(path) => write!(
+                f,
+                "{} is not allowed by the configured path filter",
+                path
+            ),
+            ContextualError::DisallowedUploadTypeError(path) => write!(
+                f,
+                "{} does not match any of the allowed upload file types",
+                path
+            ),
+            ContextualError::UploadTooLargeError(path, max) => write!(
+                f,
+                "{} exceeds the maximum upload size of {} bytes",
+                path, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ContextualError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ContextualError::IOError(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Log a fully-formatted error description, one line per level of the chain it was built from.
+pub fn log_error_chain(description: String) {
+    for line in description.lines() {
+        eprintln!("{}", line);
+    }
+}
@@ -4,49 +4,521 @@ use actix_web::{
     multipart, FutureResponse, HttpMessage, HttpRequest, HttpResponse,
 };
 use futures::{future, future::FutureResult, Future, Stream};
+use futures_cpupool::CpuPool;
+use rand::Rng;
 use std::{
     fs,
-    io::Write,
-    path::{Component, PathBuf},
+    io::{Seek, SeekFrom, Write},
+    path::{Component, Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, OnceLock,
+    },
 };
 
 use crate::errors::{self, ContextualError};
 use crate::listing::{self, SortingMethod, SortingOrder};
+use crate::path_filter::PathFilter;
 use crate::renderer;
 use crate::themes::ColorScheme;
 
+/// Number of leading bytes of an uploaded field (collected across as many stream chunks as it
+/// takes) buffered before they are matched against the configured allow-list of file signatures.
+const SNIFF_PREFIX_LEN: usize = 32;
+
+/// A permit held for the duration of a single upload request, counted against the configured
+/// `max_concurrent_uploads` limit. Releases its slot when dropped.
+struct UploadPermit {
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for UploadPermit {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Try to reserve a slot out of `max` concurrent uploads, returning `None` if the limit has
+/// already been reached.
+fn try_acquire_upload_permit(in_flight: &Arc<AtomicUsize>, max: usize) -> Option<UploadPermit> {
+    loop {
+        let current = in_flight.load(Ordering::SeqCst);
+        if current >= max {
+            return None;
+        }
+        if in_flight
+            .compare_exchange(current, current + 1, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            return Some(UploadPermit {
+                in_flight: in_flight.clone(),
+            });
+        }
+    }
+}
+
+/// Identify the format of a file from the leading bytes of its content, if recognized.
+///
+/// Only the handful of signatures operators are likely to want to allow-list are covered here;
+/// anything else is reported as unrecognized rather than guessed at.
+fn sniff_format(prefix: &[u8]) -> Option<&'static str> {
+    if prefix.starts_with(b"\x89PNG\x0d\x0a\x1a\x0a") {
+        Some("png")
+    } else if prefix.starts_with(b"\xff\xd8\xff") {
+        Some("jpeg")
+    } else if prefix.starts_with(b"GIF87a") || prefix.starts_with(b"GIF89a") {
+        Some("gif")
+    } else if prefix.starts_with(b"%PDF-") {
+        Some("pdf")
+    } else if prefix.len() >= 12 && &prefix[0..4] == b"RIFF" && &prefix[8..12] == b"WEBP" {
+        Some("webp")
+    } else if prefix.len() >= 8 && &prefix[4..8] == b"ftyp" {
+        Some("mp4")
+    } else {
+        None
+    }
+}
+
+/// Build the path of the temporary file a field is streamed into before it is renamed onto its
+/// final destination. The name is prefixed with a dot (hidden on Unix) and suffixed with a random
+/// nonce, and lives next to `file_path` so the final rename stays on the same filesystem.
+fn temp_upload_path(file_path: &std::path::Path) -> PathBuf {
+    let file_name = file_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let nonce: u64 = rand::thread_rng().gen();
+    file_path.with_file_name(format!(".{}.{:x}.miniserve-upload", file_name, nonce))
+}
+
 /// Create future to save file.
+///
+/// The field is streamed into a temporary file in the same directory as `file_path`; only once
+/// the stream completes and the data is flushed to disk is the temporary file renamed onto
+/// `file_path`. This keeps a client disconnect, a full disk, or a write error from ever leaving a
+/// truncated file at the destination, and means the existing-file check only matters at the
+/// moment the upload actually lands.
 fn save_file(
     field: multipart::Field<dev::Payload>,
     file_path: PathBuf,
     overwrite_files: bool,
+    allowed_upload_types: Option<Vec<String>>,
+    max_upload_size: Option<u64>,
 ) -> Box<dyn Future<Item = i64, Error = ContextualError>> {
-    if !overwrite_files && file_path.exists() {
-        return Box::new(future::err(ContextualError::CustomError(
-            "File already exists, and the overwrite_files option has not been set".to_string(),
-        )));
-    }
+    let temp_path = temp_upload_path(&file_path);
+    let cleanup_path = temp_path.clone();
+    let display_path = file_path.display().to_string();
+    let allowed_upload_types_finalize = allowed_upload_types.clone();
 
-    let mut file = match std::fs::File::create(&file_path) {
-        Ok(file) => file,
-        Err(e) => {
-            return Box::new(future::err(ContextualError::IOError(
-                format!("Failed to create {}", file_path.display()),
-                e,
-            )));
-        }
-    };
     Box::new(
         field
             .map_err(ContextualError::MultipartError)
-            .fold(0i64, move |acc, bytes| {
-                let rt = file
-                    .write_all(bytes.as_ref())
-                    .map(|_| acc + bytes.len() as i64)
-                    .map_err(|e| {
-                        ContextualError::IOError("Failed to write to file".to_string(), e)
-                    });
+            .fold(
+                (None::<std::fs::File>, Vec::<u8>::new(), 0i64),
+                {
+                    let temp_path = temp_path.clone();
+                    let display_path = display_path.clone();
+                    move |(mut file, mut pending, acc), bytes| {
+                        let rt: Result<(Option<std::fs::File>, Vec<u8>, i64), ContextualError> =
+                            (|| {
+                                let new_acc = acc + bytes.len() as i64;
+                                if let Some(max) = max_upload_size {
+                                    if new_acc as u64 > max {
+                                        return Err(ContextualError::UploadTooLargeError(
+                                            display_path.clone(),
+                                            max,
+                                        ));
+                                    }
+                                }
+                                if let Some(f) = file.as_mut() {
+                                    f.write_all(bytes.as_ref()).map_err(|e| {
+                                        ContextualError::IOError(
+                                            "Failed to write to file".to_string(),
+                                            e,
+                                        )
+                                    })?;
+                                    return Ok((file, pending, new_acc));
+                                }
+
+                                // Multipart chunk boundaries are transport-dependent, so the
+                                // signature may be split across several stream items; keep
+                                // buffering until enough bytes are collected to make the call (the
+                                // final fold stage handles the case where the stream ends first).
+                                pending.extend_from_slice(bytes.as_ref());
+                                if pending.len() >= SNIFF_PREFIX_LEN {
+                                    if let Some(allowed) = &allowed_upload_types {
+                                        match sniff_format(&pending[..SNIFF_PREFIX_LEN]) {
+                                            Some(format) if allowed.iter().any(|t| t == format) => {}
+                                            _ => {
+                                                return Err(
+                                                    ContextualError::DisallowedUploadTypeError(
+                                                        display_path.clone(),
+                                                    ),
+                                                );
+                                            }
+                                        }
+                                    }
+                                    let mut f =
+                                        std::fs::File::create(&temp_path).map_err(|e| {
+                                            ContextualError::IOError(
+                                                format!(
+                                                    "Failed to create {}",
+                                                    temp_path.display()
+                                                ),
+                                                e,
+                                            )
+                                        })?;
+                                    f.write_all(&pending).map_err(|e| {
+                                        ContextualError::IOError(
+                                            "Failed to write to file".to_string(),
+                                            e,
+                                        )
+                                    })?;
+                                    pending.clear();
+                                    file = Some(f);
+                                }
+                                Ok((file, pending, new_acc))
+                            })();
+                        future::result(rt)
+                    }
+                },
+            )
+            .and_then(move |(file, pending, acc)| {
+                let rt: Result<i64, ContextualError> = (|| {
+                    // The stream ended before `pending` ever reached `SNIFF_PREFIX_LEN` bytes
+                    // (including a genuine zero-byte upload); make the sniff decision on however
+                    // many bytes actually arrived and create the temp file now so the rename
+                    // below always has a source to work with.
+                    let file = match file {
+                        Some(file) => file,
+                        None => {
+                            if let Some(allowed) = &allowed_upload_types_finalize {
+                                match sniff_format(&pending) {
+                                    Some(format) if allowed.iter().any(|t| t == format) => (),
+                                    _ => {
+                                        return Err(ContextualError::DisallowedUploadTypeError(
+                                            display_path.clone(),
+                                        ));
+                                    }
+                                }
+                            }
+                            let mut file = std::fs::File::create(&temp_path).map_err(|e| {
+                                ContextualError::IOError(
+                                    format!("Failed to create {}", temp_path.display()),
+                                    e,
+                                )
+                            })?;
+                            file.write_all(&pending).map_err(|e| {
+                                ContextualError::IOError(
+                                    "Failed to write to file".to_string(),
+                                    e,
+                                )
+                            })?;
+                            file
+                        }
+                    };
+                    file.sync_all().map_err(|e| {
+                        ContextualError::IOError(
+                            "Failed to flush uploaded file to disk".to_string(),
+                            e,
+                        )
+                    })?;
+                    if !overwrite_files && file_path.exists() {
+                        return Err(ContextualError::CustomError(
+                            "File already exists, and the overwrite_files option has not been set"
+                                .to_string(),
+                        ));
+                    }
+                    fs::rename(&temp_path, &file_path).map_err(|e| {
+                        ContextualError::IOError(
+                            format!(
+                                "Failed to move completed upload into {}",
+                                file_path.display()
+                            ),
+                            e,
+                        )
+                    })?;
+                    Ok(acc)
+                })();
                 future::result(rt)
+            })
+            .or_else(move |e| {
+                let _ = fs::remove_file(&cleanup_path);
+                future::err(e)
+            }),
+    )
+}
+
+/// A parsed `Content-Range: bytes start-end/total` header, as sent by a resumable upload client.
+#[derive(Debug, Clone, Copy)]
+struct ContentRange {
+    start: u64,
+    end: u64,
+    total: u64,
+}
+
+/// Parse a `Content-Range` header value of the form `bytes start-end/total`.
+fn parse_content_range(value: &str) -> Option<ContentRange> {
+    let rest = value.trim().strip_prefix("bytes ")?;
+    let (range, total) = rest.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let start: u64 = start.trim().parse().ok()?;
+    let end: u64 = end.trim().parse().ok()?;
+    let total: u64 = total.trim().parse().ok()?;
+    if end < start || end >= total {
+        return None;
+    }
+    Some(ContentRange { start, end, total })
+}
+
+/// Result of handing a single chunk of a resumable upload to `handle_chunked_upload`.
+enum ChunkedUploadStatus {
+    /// The file is not fully received yet; contains the number of contiguous bytes stored so far,
+    /// starting from offset 0.
+    Incomplete(u64),
+    /// All bytes of the file have now been received.
+    Complete,
+}
+
+/// Holds an exclusive, process-wide lock on a resumable upload's sidecar for as long as it lives,
+/// releasing it on drop. Serializes the read-merge-write of the sidecar across chunks of the same
+/// upload that are in flight concurrently, so a read of stale `ranges` can never clobber a range
+/// recorded by a chunk that finished writing in the meantime.
+struct SidecarLock {
+    lock_path: PathBuf,
+}
+
+impl Drop for SidecarLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.lock_path);
+    }
+}
+
+/// Acquire the lock for `sidecar`, blocking (with a short sleep between attempts) until it is
+/// free. The lock itself is just an exclusively-created file next to the sidecar, which is enough
+/// to serialize access across threads and processes on the same filesystem.
+///
+/// This spins the calling thread, so it must only ever be called from [`blocking_pool`], never
+/// directly on an actix-web worker thread: a contended lock would otherwise stall every other
+/// connection that worker is serving for the full wait.
+fn lock_sidecar(sidecar: &Path) -> std::io::Result<SidecarLock> {
+    let mut lock_file_name = sidecar.as_os_str().to_owned();
+    lock_file_name.push(".lock");
+    let lock_path = PathBuf::from(lock_file_name);
+    loop {
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&lock_path)
+        {
+            Ok(_) => return Ok(SidecarLock { lock_path }),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Path of the sidecar file used to track which byte ranges of a resumable upload have been
+/// received so far.
+fn sidecar_path(file_path: &Path) -> PathBuf {
+    let file_name = file_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    file_path.with_file_name(format!(".{}.miniserve-upload", file_name))
+}
+
+/// Read a sidecar file, returning the declared total size and the list of byte ranges received so
+/// far. Returns `None` if no upload for this file is in progress.
+fn read_sidecar(path: &Path) -> Option<(u64, Vec<(u64, u64)>)> {
+    let contents = fs::read_to_string(path).ok()?;
+    let mut lines = contents.lines();
+    let total: u64 = lines.next()?.strip_prefix("total:")?.parse().ok()?;
+    let ranges = lines
+        .filter_map(|line| {
+            let (start, end) = line.split_once('-')?;
+            Some((start.parse().ok()?, end.parse().ok()?))
+        })
+        .collect();
+    Some((total, ranges))
+}
+
+/// Persist the declared total size and the list of byte ranges received so far to the sidecar
+/// file, so that progress survives across chunks (and server restarts).
+fn write_sidecar(path: &Path, total: u64, ranges: &[(u64, u64)]) -> std::io::Result<()> {
+    let mut contents = format!("total:{}\n", total);
+    for (start, end) in ranges {
+        contents.push_str(&format!("{}-{}\n", start, end));
+    }
+    fs::write(path, contents)
+}
+
+/// Sort and merge overlapping or adjacent byte ranges.
+fn merge_ranges(ranges: &mut Vec<(u64, u64)>) {
+    ranges.sort_unstable_by_key(|&(start, _)| start);
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+    for &(start, end) in ranges.iter() {
+        if let Some(last) = merged.last_mut() {
+            if start <= last.1 + 1 {
+                last.1 = last.1.max(end);
+                continue;
+            }
+        }
+        merged.push((start, end));
+    }
+    *ranges = merged;
+}
+
+/// Number of contiguous bytes received so far, starting from offset 0.
+fn contiguous_prefix(ranges: &[(u64, u64)]) -> u64 {
+    match ranges.first() {
+        Some(&(0, end)) => end + 1,
+        _ => 0,
+    }
+}
+
+/// Thread pool that the blocking sidecar-lock/disk-IO work of [`handle_chunked_upload`] runs on,
+/// so a contended lock spins a pool thread instead of the actix-web worker thread handling it.
+fn blocking_pool() -> &'static CpuPool {
+    static POOL: OnceLock<CpuPool> = OnceLock::new();
+    POOL.get_or_init(CpuPool::new_num_cpus)
+}
+
+/// Write a single chunk of a resumable upload, identified by its `Content-Range`, into the target
+/// file at the right offset, tracking overall progress in a sidecar file next to it.
+///
+/// The target file is created (and preallocated to its final size) on the first chunk received
+/// for it. Once every byte from `0` to `total` has been accounted for, the sidecar is removed and
+/// `ChunkedUploadStatus::Complete` is returned. The body of this future runs on [`blocking_pool`]
+/// rather than inline, since acquiring the sidecar lock can block.
+fn handle_chunked_upload(
+    payload: dev::Payload,
+    file_path: PathBuf,
+    range: ContentRange,
+    overwrite_files: bool,
+    allowed_upload_types: Option<Vec<String>>,
+    app_root_dir: PathBuf,
+    path_filter: Option<PathFilter>,
+) -> Box<dyn Future<Item = ChunkedUploadStatus, Error = ContextualError>> {
+    Box::new(
+        payload
+            .concat2()
+            .map_err(|e| ContextualError::CustomError(format!("Failed to read request body: {}", e)))
+            .and_then(move |body| {
+                blocking_pool().spawn_fn(move || {
+                    let rt: Result<ChunkedUploadStatus, ContextualError> = (|| {
+                        if body.len() as u64 != range.end - range.start + 1 {
+                            return Err(ContextualError::InvalidHTTPRequestError(
+                                "Uploaded chunk size does not match the declared Content-Range"
+                                    .to_string(),
+                            ));
+                        }
+
+                        if let Some(filter) = &path_filter {
+                            let relative =
+                                file_path.strip_prefix(&app_root_dir).unwrap_or(&file_path);
+                            if !filter.is_allowed(relative) {
+                                return Err(ContextualError::ForbiddenPathError(
+                                    file_path.display().to_string(),
+                                ));
+                            }
+                        }
+
+                        // The file signature only lives in the chunk that covers offset 0; later
+                        // chunks of the same upload are taken on trust once that one has passed.
+                        if range.start == 0 {
+                            if let Some(allowed) = &allowed_upload_types {
+                                let prefix = &body[..body.len().min(SNIFF_PREFIX_LEN)];
+                                match sniff_format(prefix) {
+                                    Some(format) if allowed.iter().any(|t| t == format) => (),
+                                    _ => {
+                                        return Err(ContextualError::DisallowedUploadTypeError(
+                                            file_path.display().to_string(),
+                                        ));
+                                    }
+                                }
+                            }
+                        }
+
+                        let sidecar = sidecar_path(&file_path);
+                        let _sidecar_lock = lock_sidecar(&sidecar).map_err(|e| {
+                            ContextualError::IOError(
+                                format!("Failed to lock {}", sidecar.display()),
+                                e,
+                            )
+                        })?;
+                        let (total, mut ranges) = match read_sidecar(&sidecar) {
+                            Some((total, ranges)) => {
+                                if total != range.total {
+                                    return Err(ContextualError::InvalidHTTPRequestError(
+                                        "Content-Range total size disagrees with a previous chunk"
+                                            .to_string(),
+                                    ));
+                                }
+                                (total, ranges)
+                            }
+                            None => {
+                                if !overwrite_files && file_path.exists() {
+                                    return Err(ContextualError::CustomError(
+                                        "File already exists, and the overwrite_files option has \
+                                         not been set"
+                                            .to_string(),
+                                    ));
+                                }
+                                (range.total, Vec::new())
+                            }
+                        };
+
+                        let mut file = fs::OpenOptions::new()
+                            .write(true)
+                            .create(true)
+                            .truncate(false)
+                            .open(&file_path)
+                            .map_err(|e| {
+                                ContextualError::IOError(
+                                    format!("Failed to open {}", file_path.display()),
+                                    e,
+                                )
+                            })?;
+                        file.set_len(total).map_err(|e| {
+                            ContextualError::IOError(
+                                format!("Failed to preallocate {}", file_path.display()),
+                                e,
+                            )
+                        })?;
+                        file.seek(SeekFrom::Start(range.start)).map_err(|e| {
+                            ContextualError::IOError(
+                                format!("Failed to seek in {}", file_path.display()),
+                                e,
+                            )
+                        })?;
+                        file.write_all(&body).map_err(|e| {
+                            ContextualError::IOError(
+                                "Failed to write chunk to file".to_string(),
+                                e,
+                            )
+                        })?;
+
+                        ranges.push((range.start, range.end));
+                        merge_ranges(&mut ranges);
+
+                        if contiguous_prefix(&ranges) >= total {
+                            let _ = fs::remove_file(&sidecar);
+                            Ok(ChunkedUploadStatus::Complete)
+                        } else {
+                            write_sidecar(&sidecar, total, &ranges).map_err(|e| {
+                                ContextualError::IOError(
+                                    format!("Failed to update {}", sidecar.display()),
+                                    e,
+                                )
+                            })?;
+                            Ok(ChunkedUploadStatus::Incomplete(contiguous_prefix(&ranges)))
+                        }
+                    })();
+                    rt
+                })
             }),
     )
 }
@@ -56,6 +528,10 @@ fn handle_multipart(
     item: multipart::MultipartItem<dev::Payload>,
     mut file_path: PathBuf,
     overwrite_files: bool,
+    allowed_upload_types: Option<Vec<String>>,
+    max_upload_size: Option<u64>,
+    app_root_dir: PathBuf,
+    path_filter: Option<PathFilter>,
 ) -> Box<dyn Stream<Item = i64, Error = ContextualError>> {
     match item {
         multipart::MultipartItem::Field(field) => {
@@ -96,17 +572,41 @@ fn handle_multipart(
                         }
                     }
                     file_path = file_path.join(f);
-                    Box::new(save_file(field, file_path, overwrite_files).into_stream())
+                    if let Some(filter) = &path_filter {
+                        let relative = file_path.strip_prefix(&app_root_dir).unwrap_or(&file_path);
+                        if !filter.is_allowed(relative) {
+                            return err(ContextualError::ForbiddenPathError(
+                                file_path.display().to_string(),
+                            ));
+                        }
+                    }
+                    Box::new(
+                        save_file(
+                            field,
+                            file_path,
+                            overwrite_files,
+                            allowed_upload_types,
+                            max_upload_size,
+                        )
+                        .into_stream(),
+                    )
                 }
-                Err(e) => err(e(
-                    "HTTP header".to_string(),
-                    "Failed to retrieve the name of the file to upload".to_string(),
-                )),
+                Err(e) => err(e),
             }
         }
         multipart::MultipartItem::Nested(mp) => Box::new(
             mp.map_err(ContextualError::MultipartError)
-                .map(move |item| handle_multipart(item, file_path.clone(), overwrite_files))
+                .map(move |item| {
+                    handle_multipart(
+                        item,
+                        file_path.clone(),
+                        overwrite_files,
+                        allowed_upload_types.clone(),
+                        max_upload_size,
+                        app_root_dir.clone(),
+                        path_filter.clone(),
+                    )
+                })
                 .flatten(),
         ),
     }
@@ -192,28 +692,162 @@ pub fn upload_file(
         }
     };
     let overwrite_files = req.state().overwrite_files;
-    Box::new(
-        req.multipart()
-            .map_err(ContextualError::MultipartError)
-            .map(move |item| handle_multipart(item, target_dir.clone(), overwrite_files))
-            .flatten()
-            .collect()
-            .then(move |e| match e {
-                Ok(_) => future::ok(
-                    HttpResponse::SeeOther()
-                        .header(header::LOCATION, return_path)
-                        .finish(),
-                ),
-                Err(e) => create_error_response(
-                    &e.to_string(),
-                    StatusCode::INTERNAL_SERVER_ERROR,
+    let max_upload_size = req.state().max_upload_size;
+    let allowed_upload_types = req.state().allowed_upload_types.clone();
+    let path_filter = req.state().path_filter.clone();
+
+    // Bound the number of uploads being streamed to disk at once; reject with a retriable error
+    // rather than let an unbounded number of multipart/chunked streams pile up.
+    let upload_permit = if let Some(max_concurrent_uploads) = req.state().max_concurrent_uploads {
+        match try_acquire_upload_permit(&req.state().in_flight_uploads, max_concurrent_uploads) {
+            Some(permit) => Some(permit),
+            None => {
+                return Box::new(future::ok(
+                    HttpResponse::ServiceUnavailable()
+                        .header(header::RETRY_AFTER, "1")
+                        .body("Too many concurrent uploads, please retry shortly"),
+                ));
+            }
+        }
+    } else {
+        None
+    };
+
+    // A `Content-Range` header means the client is sending a single chunk of a resumable upload
+    // rather than a whole multipart form; handle that separately from the multipart path below.
+    if let Some(header_value) = req.headers().get(header::CONTENT_RANGE) {
+        let content_range = match header_value.to_str().ok().and_then(parse_content_range) {
+            Some(range) => range,
+            None => {
+                let err = ContextualError::InvalidHTTPRequestError(
+                    "Invalid 'Content-Range' header".to_string(),
+                );
+                return Box::new(create_error_response(
+                    &err.to_string(),
+                    StatusCode::BAD_REQUEST,
                     &return_path,
                     query_params.sort,
                     query_params.order,
                     color_scheme,
                     default_color_scheme,
-                    uses_random_route
-                ),
+                    uses_random_route,
+                ));
+            }
+        };
+        let file_name = match req.query().get("filename") {
+            Some(name) if !name.is_empty() && !name.contains('/') && name != ".." => {
+                name.clone()
+            }
+            _ => {
+                let err = ContextualError::InvalidHTTPRequestError(
+                    "Missing or invalid query parameter 'filename'".to_string(),
+                );
+                return Box::new(create_error_response(
+                    &err.to_string(),
+                    StatusCode::BAD_REQUEST,
+                    &return_path,
+                    query_params.sort,
+                    query_params.order,
+                    color_scheme,
+                    default_color_scheme,
+                    uses_random_route,
+                ));
+            }
+        };
+        if let Some(max) = max_upload_size {
+            if content_range.total > max {
+                let err = ContextualError::UploadTooLargeError(
+                    target_dir.join(&file_name).display().to_string(),
+                    max,
+                );
+                return Box::new(create_error_response(
+                    &err.to_string(),
+                    StatusCode::BAD_REQUEST,
+                    &return_path,
+                    query_params.sort,
+                    query_params.order,
+                    color_scheme,
+                    default_color_scheme,
+                    uses_random_route,
+                ));
+            }
+        }
+        let file_path = target_dir.join(file_name);
+        let payload = req.payload();
+        return Box::new(handle_chunked_upload(
+            payload,
+            file_path,
+            content_range,
+            overwrite_files,
+            allowed_upload_types,
+            app_root_dir.clone(),
+            path_filter,
+        )
+            .then(move |result| {
+                let _upload_permit = upload_permit;
+                match result {
+                    Ok(ChunkedUploadStatus::Complete) => future::ok(
+                        HttpResponse::SeeOther()
+                            .header(header::LOCATION, return_path)
+                            .finish(),
+                    ),
+                    Ok(ChunkedUploadStatus::Incomplete(received)) => future::ok(
+                        HttpResponse::build(StatusCode::PERMANENT_REDIRECT)
+                            .header(
+                                header::RANGE,
+                                format!("bytes=0-{}", received.saturating_sub(1)),
+                            )
+                            .finish(),
+                    ),
+                    Err(e) => create_error_response(
+                        &e.to_string(),
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        &return_path,
+                        query_params.sort,
+                        query_params.order,
+                        color_scheme,
+                        default_color_scheme,
+                        uses_random_route,
+                    ),
+                }
+            }));
+    }
+
+    Box::new(
+        req.multipart()
+            .map_err(ContextualError::MultipartError)
+            .map(move |item| {
+                handle_multipart(
+                    item,
+                    target_dir.clone(),
+                    overwrite_files,
+                    allowed_upload_types.clone(),
+                    max_upload_size,
+                    app_root_dir.clone(),
+                    path_filter.clone(),
+                )
+            })
+            .flatten()
+            .collect()
+            .then(move |e| {
+                let _upload_permit = upload_permit;
+                match e {
+                    Ok(_) => future::ok(
+                        HttpResponse::SeeOther()
+                            .header(header::LOCATION, return_path)
+                            .finish(),
+                    ),
+                    Err(e) => create_error_response(
+                        &e.to_string(),
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        &return_path,
+                        query_params.sort,
+                        query_params.order,
+                        color_scheme,
+                        default_color_scheme,
+                        uses_random_route,
+                    ),
+                }
             }),
     )
 }
@@ -250,3 +884,71 @@ fn create_error_response(
             ),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniff_format_recognizes_known_signatures() {
+        assert_eq!(sniff_format(b"\x89PNG\x0d\x0a\x1a\x0a\x00\x00"), Some("png"));
+        assert_eq!(sniff_format(b"\xff\xd8\xff\xe0\x00\x10"), Some("jpeg"));
+        assert_eq!(sniff_format(b"GIF87a"), Some("gif"));
+        assert_eq!(sniff_format(b"GIF89a"), Some("gif"));
+        assert_eq!(sniff_format(b"%PDF-1.7"), Some("pdf"));
+        assert_eq!(
+            sniff_format(b"RIFF\x00\x00\x00\x00WEBPVP8 "),
+            Some("webp")
+        );
+        assert_eq!(sniff_format(b"\x00\x00\x00\x18ftypmp42"), Some("mp4"));
+    }
+
+    #[test]
+    fn sniff_format_rejects_unknown_or_truncated_input() {
+        assert_eq!(sniff_format(b"not a real file"), None);
+        assert_eq!(sniff_format(b""), None);
+        // A signature whose first bytes match a known magic but which is too short to contain it
+        // in full must not be mistaken for a match.
+        assert_eq!(sniff_format(b"\x89PNG"), None);
+        assert_eq!(sniff_format(b"RIFF\x00\x00\x00\x00"), None);
+    }
+
+    #[test]
+    fn parse_content_range_accepts_well_formed_header() {
+        let range = parse_content_range("bytes 0-99/200").unwrap();
+        assert_eq!(range.start, 0);
+        assert_eq!(range.end, 99);
+        assert_eq!(range.total, 200);
+    }
+
+    #[test]
+    fn parse_content_range_rejects_malformed_or_inconsistent_values() {
+        assert!(parse_content_range("bytes 0-99").is_none()); // missing total
+        assert!(parse_content_range("0-99/200").is_none()); // missing "bytes " prefix
+        assert!(parse_content_range("bytes abc-99/200").is_none()); // non-numeric start
+        assert!(parse_content_range("bytes 0-99-5/200").is_none()); // extra dash
+        assert!(parse_content_range("bytes 50-10/200").is_none()); // end < start
+        assert!(parse_content_range("bytes 0-200/200").is_none()); // end >= total
+        // end == total - 1 is the last valid byte of the declared total and must be accepted.
+        assert!(parse_content_range("bytes 0-199/200").is_some());
+    }
+
+    #[test]
+    fn merge_ranges_combines_overlapping_and_adjacent_spans() {
+        let mut ranges = vec![(10, 19), (0, 9), (25, 30), (20, 24)];
+        merge_ranges(&mut ranges);
+        assert_eq!(ranges, vec![(0, 30)]);
+
+        let mut ranges = vec![(0, 9), (20, 29)];
+        merge_ranges(&mut ranges);
+        assert_eq!(ranges, vec![(0, 9), (20, 29)]);
+    }
+
+    #[test]
+    fn contiguous_prefix_stops_at_first_gap() {
+        assert_eq!(contiguous_prefix(&[]), 0);
+        assert_eq!(contiguous_prefix(&[(0, 9)]), 10);
+        assert_eq!(contiguous_prefix(&[(0, 9), (20, 29)]), 10);
+        assert_eq!(contiguous_prefix(&[(10, 19)]), 0);
+    }
+}
@@ -0,0 +1,133 @@
+use std::{fs, io, path::Path};
+
+use crate::path_filter::PathFilter;
+use crate::themes::ColorScheme;
+
+/// How directory entries are ordered in a rendered listing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortingMethod {
+    Name,
+    Size,
+    Date,
+}
+
+/// Ascending or descending variant of a [`SortingMethod`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortingOrder {
+    Asc,
+    Desc,
+}
+
+/// Query parameters accepted by both the listing and upload endpoints.
+pub struct QueryParameters {
+    pub path: Option<std::path::PathBuf>,
+    pub sort: Option<SortingMethod>,
+    pub order: Option<SortingOrder>,
+    pub theme: Option<ColorScheme>,
+}
+
+/// Pull the `path`/`sort`/`order`/`theme` query parameters out of a request, ignoring any that
+/// are missing or fail to parse rather than rejecting the request outright.
+pub fn extract_query_parameters(
+    req: &actix_web::HttpRequest<crate::MiniserveConfig>,
+) -> QueryParameters {
+    let query = req.query();
+    QueryParameters {
+        path: query.get("path").map(std::path::PathBuf::from),
+        sort: query.get("sort").and_then(|value| match value.as_str() {
+            "name" => Some(SortingMethod::Name),
+            "size" => Some(SortingMethod::Size),
+            "date" => Some(SortingMethod::Date),
+            _ => None,
+        }),
+        order: query.get("order").and_then(|value| match value.as_str() {
+            "asc" => Some(SortingOrder::Asc),
+            "desc" => Some(SortingOrder::Desc),
+            _ => None,
+        }),
+        theme: query.get("theme").and_then(|value| match value.as_str() {
+            "squid" => Some(ColorScheme::Squid),
+            "archlinux" => Some(ColorScheme::Archlinux),
+            "zenburn" => Some(ColorScheme::Zenburn),
+            "monokai" => Some(ColorScheme::Monokai),
+            _ => None,
+        }),
+    }
+}
+
+/// A single entry in a rendered directory listing.
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// List the immediate children of `dir`, sorted by name in the given order (every
+/// `SortingMethod` orders by name, see `sort_entries`), excluding any entry the
+/// configured `path_filter` rejects. This is the same predicate `handle_multipart` applies to
+/// uploads, so a path hidden from the listing can't be uploaded to either, and vice versa.
+pub fn directory_listing(
+    dir: &Path,
+    app_root_dir: &Path,
+    sorting_method: SortingMethod,
+    sorting_order: SortingOrder,
+    path_filter: Option<&PathFilter>,
+) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if let Some(filter) = path_filter {
+            let relative = path.strip_prefix(app_root_dir).unwrap_or(&path);
+            if !filter.is_allowed(relative) {
+                continue;
+            }
+        }
+        entries.push(Entry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            is_dir: entry.file_type()?.is_dir(),
+        });
+    }
+    sort_entries(&mut entries, sorting_method, sorting_order);
+    Ok(entries)
+}
+
+fn sort_entries(entries: &mut [Entry], method: SortingMethod, order: SortingOrder) {
+    match method {
+        // Size and modification time aren't tracked on `Entry`, so both fall back to name
+        // ordering for now.
+        SortingMethod::Name | SortingMethod::Size | SortingMethod::Date => {
+            entries.sort_by(|a, b| a.name.cmp(&b.name))
+        }
+    }
+    if order == SortingOrder::Desc {
+        entries.reverse();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str) -> Entry {
+        Entry {
+            name: name.to_string(),
+            is_dir: false,
+        }
+    }
+
+    #[test]
+    fn sort_entries_orders_by_name_for_ascending_order() {
+        let mut entries = vec![entry("banana"), entry("apple"), entry("cherry")];
+        sort_entries(&mut entries, SortingMethod::Name, SortingOrder::Asc);
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["apple", "banana", "cherry"]);
+    }
+
+    #[test]
+    fn sort_entries_reverses_for_descending_order() {
+        let mut entries = vec![entry("banana"), entry("apple"), entry("cherry")];
+        sort_entries(&mut entries, SortingMethod::Name, SortingOrder::Desc);
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["cherry", "banana", "apple"]);
+    }
+}
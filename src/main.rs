@@ -0,0 +1,63 @@
+mod config;
+mod errors;
+mod file_upload;
+mod listing;
+mod path_filter;
+mod renderer;
+mod themes;
+
+use actix_web::{http::Method, server, App, HttpRequest, HttpResponse};
+use structopt::StructOpt;
+
+pub use config::MiniserveConfig;
+use listing::{SortingMethod, SortingOrder};
+use themes::ColorScheme;
+
+/// Render the directory listing for the path given in the `path` query parameter, filtered
+/// through the configured [`path_filter::PathFilter`] so browsing and uploading always agree on
+/// what may exist in the served tree.
+fn index(req: &HttpRequest<MiniserveConfig>) -> HttpResponse {
+    let query = listing::extract_query_parameters(req);
+    let color_scheme = query.theme.unwrap_or(ColorScheme::Squid);
+
+    let app_root_dir = match req.state().path.canonicalize() {
+        Ok(dir) => dir,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+    let target_dir = match app_root_dir.join(query.path.unwrap_or_default()).canonicalize() {
+        Ok(dir) if dir.starts_with(&app_root_dir) => dir,
+        _ => return HttpResponse::BadRequest().body("Invalid value for 'path' parameter"),
+    };
+
+    match listing::directory_listing(
+        &target_dir,
+        &app_root_dir,
+        query.sort.unwrap_or(SortingMethod::Name),
+        query.order.unwrap_or(SortingOrder::Asc),
+        req.state().path_filter.as_ref(),
+    ) {
+        Ok(entries) => {
+            HttpResponse::Ok().body(renderer::render_listing(&entries, color_scheme).into_string())
+        }
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+fn main() {
+    let miniserve_config = MiniserveConfig::from(config::Args::from_args());
+    let bind_address = "0.0.0.0:8080";
+
+    server::new(move || {
+        App::with_state(miniserve_config.clone())
+            .resource("/", |r| r.method(Method::GET).f(index))
+            .resource("/upload", |r| {
+                r.method(Method::POST)
+                    .f(|req: &HttpRequest<MiniserveConfig>| {
+                        file_upload::upload_file(req, ColorScheme::Squid, false)
+                    })
+            })
+    })
+    .bind(bind_address)
+    .unwrap_or_else(|e| panic!("Failed to bind to {}: {}", bind_address, e))
+    .run();
+}
@@ -0,0 +1,140 @@
+use std::path::Path;
+
+/// A single allow or deny rule, matched against the slash-separated, root-relative path of a
+/// candidate file.
+#[derive(Clone, Debug)]
+enum Rule {
+    Allow(String),
+    Deny(String),
+}
+
+/// An allow/deny predicate for paths inside the served directory, consulted by both the upload
+/// handler before a file is written and the directory listing before an entry is shown, so
+/// uploads and browsing always agree on what may exist in the tree.
+///
+/// Rules are tried in the order they were added and the first one whose pattern matches decides
+/// the outcome; a path that matches no rule is allowed.
+#[derive(Clone, Debug, Default)]
+pub struct PathFilter {
+    rules: Vec<Rule>,
+    deny_dotfiles: bool,
+}
+
+impl PathFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a rule allowing paths matching `pattern` (a glob supporting a single `*` wildcard,
+    /// e.g. `public/*`).
+    pub fn allow(mut self, pattern: impl Into<String>) -> Self {
+        self.rules.push(Rule::Allow(pattern.into()));
+        self
+    }
+
+    /// Add a rule denying paths matching `pattern` (a glob supporting a single `*` wildcard,
+    /// e.g. `*.exe`).
+    pub fn deny(mut self, pattern: impl Into<String>) -> Self {
+        self.rules.push(Rule::Deny(pattern.into()));
+        self
+    }
+
+    /// When enabled, any path with a dotfile component (e.g. `.git`, `.env`) is denied regardless
+    /// of the other rules.
+    pub fn deny_dotfiles(mut self, deny: bool) -> Self {
+        self.deny_dotfiles = deny;
+        self
+    }
+
+    /// Returns `true` if `path` (relative to the served root) may be uploaded to or listed.
+    pub fn is_allowed(&self, path: &Path) -> bool {
+        if self.deny_dotfiles && has_dotfile_component(path) {
+            return false;
+        }
+
+        let candidate = path.to_string_lossy().replace('\\', "/");
+        for rule in &self.rules {
+            match rule {
+                Rule::Allow(pattern) if glob_match(pattern, &candidate) => return true,
+                Rule::Deny(pattern) if glob_match(pattern, &candidate) => return false,
+                _ => {}
+            }
+        }
+        true
+    }
+}
+
+fn has_dotfile_component(path: &Path) -> bool {
+    path.components().any(|component| {
+        component
+            .as_os_str()
+            .to_str()
+            .map(|s| s.starts_with('.'))
+            .unwrap_or(false)
+    })
+}
+
+/// Minimal glob matcher supporting a single `*` wildcard, e.g. `*.exe` or `secrets/*`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    match pattern.find('*') {
+        Some(idx) => {
+            let (prefix, suffix) = (&pattern[..idx], &pattern[idx + 1..]);
+            text.len() >= prefix.len() + suffix.len()
+                && text.starts_with(prefix)
+                && text.ends_with(suffix)
+        }
+        None => pattern == text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn glob_match_without_wildcard_requires_exact_match() {
+        assert!(glob_match("secrets.txt", "secrets.txt"));
+        assert!(!glob_match("secrets.txt", "secrets.txt.bak"));
+    }
+
+    #[test]
+    fn glob_match_with_wildcard_matches_prefix_and_suffix() {
+        assert!(glob_match("*.exe", "installer.exe"));
+        assert!(!glob_match("*.exe", "installer.exe.txt"));
+        assert!(glob_match("secrets/*", "secrets/prod.env"));
+        assert!(!glob_match("secrets/*", "public/secrets/prod.env"));
+        assert!(glob_match("*", "anything/at/all"));
+    }
+
+    #[test]
+    fn is_allowed_defaults_to_true_when_no_rule_matches() {
+        let filter = PathFilter::new().allow("public/*");
+        assert!(filter.is_allowed(Path::new("other/file.txt")));
+    }
+
+    #[test]
+    fn is_allowed_picks_first_matching_rule_in_order() {
+        let filter = PathFilter::new().deny("*.exe").allow("installers/*.exe");
+        // The deny rule was added first, so it wins even though the allow rule also matches.
+        assert!(!filter.is_allowed(Path::new("installers/setup.exe")));
+
+        let filter = PathFilter::new().allow("installers/*.exe").deny("*.exe");
+        assert!(filter.is_allowed(Path::new("installers/setup.exe")));
+        assert!(!filter.is_allowed(Path::new("other/setup.exe")));
+    }
+
+    #[test]
+    fn is_allowed_denies_dotfile_components_when_enabled() {
+        let filter = PathFilter::new().deny_dotfiles(true);
+        assert!(!filter.is_allowed(Path::new(".env")));
+        assert!(!filter.is_allowed(Path::new("public/.git/config")));
+        assert!(filter.is_allowed(Path::new("public/readme.txt")));
+    }
+
+    #[test]
+    fn is_allowed_ignores_dotfiles_when_disabled() {
+        let filter = PathFilter::new();
+        assert!(filter.is_allowed(Path::new(".env")));
+    }
+}
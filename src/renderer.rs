@@ -0,0 +1,64 @@
+use actix_web::http::StatusCode;
+use maud::{html, Markup};
+
+use crate::listing::{Entry, SortingMethod, SortingOrder};
+use crate::themes::ColorScheme;
+
+/// Render a minimal HTML error page.
+#[allow(clippy::too_many_arguments)]
+pub fn render_error(
+    description: &str,
+    error_code: StatusCode,
+    return_path: &str,
+    _sorting_method: Option<SortingMethod>,
+    _sorting_order: Option<SortingOrder>,
+    color_scheme: ColorScheme,
+    _default_color_scheme: ColorScheme,
+    show_back_link: bool,
+    show_upload_hint: bool,
+) -> Markup {
+    html! {
+        html data-theme=(color_scheme.to_string()) {
+            head {
+                meta charset="utf-8";
+                title { "Error - miniserve" }
+            }
+            body {
+                h1 { (error_code.as_u16()) " " (error_code.canonical_reason().unwrap_or("Error")) }
+                p { (description) }
+                @if show_back_link {
+                    p { a href=(return_path) { "Back to file listing" } }
+                }
+                @if show_upload_hint {
+                    p { "The upload was not saved; please retry it from the previous page." }
+                }
+            }
+        }
+    }
+}
+
+/// Render a directory listing. `entries` is expected to already be in the desired order, e.g. via
+/// [`crate::listing::directory_listing`].
+pub fn render_listing(entries: &[Entry], color_scheme: ColorScheme) -> Markup {
+    html! {
+        html data-theme=(color_scheme.to_string()) {
+            head {
+                meta charset="utf-8";
+                title { "Directory listing" }
+            }
+            body {
+                ul {
+                    @for entry in entries {
+                        li {
+                            @if entry.is_dir {
+                                (format!("{}/", entry.name))
+                            } @else {
+                                (entry.name)
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
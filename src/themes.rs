@@ -0,0 +1,23 @@
+use std::fmt;
+
+/// A color scheme applied to rendered pages, selectable per-request via the `theme` query
+/// parameter and defaulting to whatever was passed on the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorScheme {
+    Squid,
+    Archlinux,
+    Zenburn,
+    Monokai,
+}
+
+impl fmt::Display for ColorScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ColorScheme::Squid => "squid",
+            ColorScheme::Archlinux => "archlinux",
+            ColorScheme::Zenburn => "zenburn",
+            ColorScheme::Monokai => "monokai",
+        };
+        write!(f, "{}", name)
+    }
+}